@@ -0,0 +1,37 @@
+use std::fmt;
+
+/// Storage-agnostic error type returned by the [`crate::repository::Repository`] trait.
+///
+/// Keeping this separate from `rusqlite::Error` lets alternative backends
+/// (in-memory, JSON-file, ...) report failures without depending on SQLite.
+#[derive(Debug)]
+pub enum Error {
+    NotFound,
+    InvalidData(String),
+    InsertData(String),
+    UpdateData(String),
+    RemoveData(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound => write!(f, "task not found"),
+            Error::InvalidData(msg) => write!(f, "invalid task data: {msg}"),
+            Error::InsertData(msg) => write!(f, "failed to insert task: {msg}"),
+            Error::UpdateData(msg) => write!(f, "failed to update task: {msg}"),
+            Error::RemoveData(msg) => write!(f, "failed to remove task: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        match err {
+            rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+            other => Error::InvalidData(other.to_string()),
+        }
+    }
+}
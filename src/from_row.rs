@@ -0,0 +1,50 @@
+use rusqlite::Row;
+
+use crate::repository::Task;
+
+/// Maps a `rusqlite::Row` to a value, column-by-column, by position.
+///
+/// Centralizing this mapping keeps the column order used by a query and the
+/// order `from_row` reads them in next to each other, instead of repeating
+/// `row.get(0)?`/`row.get(1)?` at every `query_map`/`query_row` call site.
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> rusqlite::Result<Self>;
+}
+
+/// Convenience wrapper so call sites can write `row_extract::<Task>` instead
+/// of `Task::from_row` as a closure.
+pub fn row_extract<T: FromRow>(row: &Row) -> rusqlite::Result<T> {
+    T::from_row(row)
+}
+
+impl FromRow for Task {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Task {
+            id: row.get(0)?,
+            description: row.get(1)?,
+            project: row.get(2)?,
+            link: row.get(3)?,
+            dir_path: row.get(4)?,
+            created_at: row.get(5)?,
+            finished_at: row.get(6)?,
+            scheduled_at: row.get(7)?,
+            command: row.get(8)?,
+            attempts: row.get(9)?,
+            max_attempts: row.get(10)?,
+            failed_at: row.get(11)?,
+            display_idx: row.get(12)?,
+        })
+    }
+}
+
+impl FromRow for u32 {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        row.get(0)
+    }
+}
+
+impl FromRow for (u32, u32) {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok((row.get(0)?, row.get(1)?))
+    }
+}
@@ -1,92 +1,255 @@
-use clap::{Parser, Subcommand};
-use rusqlite::{params, Connection};
-use serde::{Deserialize, Serialize};
-use anyhow::{Result};
+mod error;
+mod from_row;
+mod migrations;
+mod repository;
 
-#[derive(Serialize, Deserialize)]
-struct Task {
-    id: u32,
-    description: String,
-    completed: bool,
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use rusqlite::Connection;
+use serde_json::json;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use repository::{NewTask, Repository, SqliteRepository};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
 }
 
 #[derive(Parser)]
 #[command(name = "tasker")]
 #[command(about = "A simple CLI task manager with SQLite")]
 struct Cli {
+    /// Output format for command results.
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    Add { description: String },
+    Add {
+        description: String,
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long)]
+        link: Option<String>,
+        #[arg(long)]
+        dir: Option<String>,
+    },
     List,
-    Complete { id: u32 },
-    Delete { id: u32 },
+    Complete {
+        /// Display index as shown by `list` (not the database id).
+        index: Option<u32>,
+        /// Database id, bypassing display-index resolution.
+        #[arg(long, conflicts_with = "index", required_unless_present = "index")]
+        id: Option<u32>,
+    },
+    Delete {
+        /// Display index as shown by `list` (not the database id).
+        index: Option<u32>,
+        /// Database id, bypassing display-index resolution.
+        #[arg(long, conflicts_with = "index", required_unless_present = "index")]
+        id: Option<u32>,
+    },
+    /// Schedule a shell command to run later, with `run-due`.
+    Schedule {
+        /// Display label shown by `list`, separate from the command that runs.
+        description: String,
+        /// Shell command to execute once due.
+        command: String,
+        /// When the task becomes due, as an RFC 3339 timestamp (e.g. 2026-07-30T09:00:00Z).
+        #[arg(long)]
+        at: String,
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long)]
+        link: Option<String>,
+        #[arg(long)]
+        dir: Option<String>,
+        #[arg(long, default_value_t = 3)]
+        max_attempts: u32,
+    },
+    /// Run every scheduled task whose due time has passed.
+    RunDue,
 }
 
 const DB_PATH: &str = "tasks.db";
 
 fn init_db() -> Result<Connection> {
-    let conn = Connection::open(DB_PATH)?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS tasks (
-            id INTEGER PRIMARY KEY,
-            description TEXT NOT NULL,
-            completed BOOLEAN NOT NULL
-        )",
-        [],
-    )?;
+    let mut conn = Connection::open(DB_PATH)?;
+    migrations::run_migrations(&mut conn)?;
     Ok(conn)
 }
 
-fn add_task(conn: &Connection, description: String) -> Result<()> {
-    conn.execute(
-        "INSERT INTO tasks (description, completed) VALUES (?1, ?2)",
-        params![description, false],
-    )?;
-    println!("Added task with ID: {}", conn.last_insert_rowid());
+fn add_task(
+    repo: &dyn Repository,
+    description: String,
+    project: Option<String>,
+    link: Option<String>,
+    dir_path: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let id = repo.insert_task(NewTask {
+        description,
+        project,
+        link,
+        dir_path,
+    })?;
+    match format {
+        OutputFormat::Text => println!("Added task with ID: {}", id),
+        OutputFormat::Json => println!("{}", serde_json::to_string(&repo.get_task(id)?)?),
+    }
     Ok(())
 }
 
-fn list_tasks(conn: &Connection) -> Result<()> {
-    let mut stmt = conn.prepare("SELECT id, description, completed FROM tasks")?;
-    let tasks = stmt.query_map([], |row| {
-        Ok(Task {
-            id: row.get(0)?,
-            description: row.get(1)?,
-            completed: row.get(2)?,
-        })
-    })?;
-
-    for task in tasks {
-        let task = task?;
-        let status = if task.completed { "[x]" } else { "[ ]" };
-        println!("{} {}: {}", task.id, status, task.description);
+fn list_tasks(repo: &dyn Repository, format: OutputFormat) -> Result<()> {
+    let tasks = repo.get_tasks()?;
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&tasks)?),
+        OutputFormat::Text => {
+            for task in tasks {
+                let status = if task.finished_at.is_some() {
+                    "[x]"
+                } else if task.failed_at.is_some() {
+                    "[!]"
+                } else {
+                    "[ ]"
+                };
+                // Display indices and raw ids share the same 1..N-ish range, so
+                // tag raw-id lines to keep them visually distinct (see chunk0-2).
+                let number = match task.display_idx {
+                    Some(idx) => idx.to_string(),
+                    None => format!("id:{}", task.id),
+                };
+                let project = task.project.as_deref().unwrap_or("-");
+                let age = OffsetDateTime::now_utc() - task.created_at;
+                println!(
+                    "{} {} ({}): {} [{}d old]",
+                    number,
+                    status,
+                    project,
+                    task.description,
+                    age.whole_days()
+                );
+            }
+        }
     }
     Ok(())
 }
 
-fn complete_task(conn: &Connection, id: u32) -> Result<()> {
-    let rows_affected = conn.execute(
-        "UPDATE tasks SET completed = ?1 WHERE id = ?2",
-        params![true, id],
-    )?;
-    if rows_affected == 0 {
-        println!("Task {} not found", id);
-    } else {
-        println!("Completed task: {}", id);
+/// Resolves the `index`/`id` pair accepted by the `complete`/`delete`
+/// subcommands to a single database id. Exactly one of the two is `Some`,
+/// enforced by clap's `conflicts_with`/`required_unless_present`, so there's
+/// never a guess between "is this a display index or a raw id". A display
+/// index with no matching active task reports [`error::Error::NotFound`]
+/// the same way an unmatched raw id does, rather than bypassing it.
+fn resolve_target(
+    repo: &dyn Repository,
+    index: Option<u32>,
+    id: Option<u32>,
+) -> std::result::Result<u32, error::Error> {
+    match (index, id) {
+        (Some(index), None) => repo.resolve_display_index(index),
+        (None, Some(id)) => Ok(id),
+        _ => unreachable!("clap requires exactly one of a display index or --id"),
+    }
+}
+
+fn complete_task(
+    repo: &dyn Repository,
+    index: Option<u32>,
+    id: Option<u32>,
+    format: OutputFormat,
+) -> Result<()> {
+    let requested = index.or(id).unwrap();
+    let result = resolve_target(repo, index, id)
+        .and_then(|id| repo.update_task(id, OffsetDateTime::now_utc()));
+    print_mutation_result(format, requested, "Completed task", "completed", result)
+}
+
+fn delete_task(
+    repo: &dyn Repository,
+    index: Option<u32>,
+    id: Option<u32>,
+    format: OutputFormat,
+) -> Result<()> {
+    let requested = index.or(id).unwrap();
+    let result = resolve_target(repo, index, id).and_then(|id| repo.remove_task(id));
+    print_mutation_result(format, requested, "Deleted task", "deleted", result)
+}
+
+/// Shared text/JSON reporting for the complete/delete commands. Success and
+/// [`error::Error::NotFound`] are printed; any other error is returned.
+fn print_mutation_result(
+    format: OutputFormat,
+    id: u32,
+    text_verb: &str,
+    json_status: &str,
+    result: std::result::Result<(), error::Error>,
+) -> Result<()> {
+    match (format, result) {
+        (OutputFormat::Text, Ok(())) => println!("{}: {}", text_verb, id),
+        (OutputFormat::Text, Err(error::Error::NotFound)) => println!("Task {} not found", id),
+        (OutputFormat::Json, Ok(())) => println!("{}", json!({"id": id, "status": json_status})),
+        (OutputFormat::Json, Err(error::Error::NotFound)) => {
+            println!("{}", json!({"id": id, "status": "not_found"}))
+        }
+        (_, Err(err)) => return Err(err.into()),
     }
     Ok(())
 }
 
-fn delete_task(conn: &Connection, id: u32) -> Result<()> {
-    let rows_affected = conn.execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
-    if rows_affected == 0 {
-        println!("Task {} not found", id);
-    } else {
-        println!("Deleted task: {}", id);
+fn schedule_task(
+    repo: &dyn Repository,
+    task: NewTask,
+    command: String,
+    at: String,
+    max_attempts: u32,
+) -> Result<()> {
+    let scheduled_at =
+        OffsetDateTime::parse(&at, &Rfc3339).context("--at must be an RFC 3339 timestamp")?;
+    let id = repo.schedule_task(task, command, scheduled_at, max_attempts)?;
+    println!("Scheduled task {} for {}", id, scheduled_at);
+    Ok(())
+}
+
+/// Runs every due task's `command` as a shell command in its `dir_path`
+/// (or the current directory). Failures are retried with exponential
+/// backoff until `max_attempts` is exhausted, at which point the task is
+/// marked failed.
+fn run_due(repo: &dyn Repository) -> Result<()> {
+    let now = OffsetDateTime::now_utc();
+    for task in repo.get_due_tasks(now)? {
+        let dir = task.dir_path.as_deref().unwrap_or(".");
+        let command = task.command.as_deref().unwrap_or_default();
+        let succeeded = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(dir)
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false);
+
+        if succeeded {
+            repo.update_task(task.id, OffsetDateTime::now_utc())?;
+            println!("Task {} ran successfully", task.id);
+        } else {
+            repo.record_failure(task.id, OffsetDateTime::now_utc())?;
+            println!("Task {} failed (attempt {})", task.id, task.attempts + 1);
+        }
     }
     Ok(())
 }
@@ -94,12 +257,20 @@ fn delete_task(conn: &Connection, id: u32) -> Result<()> {
 fn main() -> Result<()> {
     let cli = Cli::parse();
     let conn = init_db()?;
+    let repo = SqliteRepository::new(conn);
 
     match cli.command {
-        Commands::Add { description } => add_task(&conn, description)?,
-        Commands::List => list_tasks(&conn)?,
-        Commands::Complete { id } => complete_task(&conn, id)?,
-        Commands::Delete { id } => delete_task(&conn, id)?,
+        Commands::Add { description, project, link, dir } => {
+            add_task(&repo, description, project, link, dir, cli.format)?
+        }
+        Commands::List => list_tasks(&repo, cli.format)?,
+        Commands::Complete { index, id } => complete_task(&repo, index, id, cli.format)?,
+        Commands::Delete { index, id } => delete_task(&repo, index, id, cli.format)?,
+        Commands::Schedule { description, command, at, project, link, dir, max_attempts } => {
+            let task = NewTask { description, project, link, dir_path: dir };
+            schedule_task(&repo, task, command, at, max_attempts)?
+        }
+        Commands::RunDue => run_due(&repo)?,
     }
 
     Ok(())
@@ -109,62 +280,136 @@ fn main() -> Result<()> {
 mod tests {
     use super::*;
 
-    fn setup_test_db() -> Connection {
-        let conn = Connection::open(":memory:").unwrap();
-        conn.execute(
-            "CREATE TABLE tasks (
-                id INTEGER PRIMARY KEY,
-                description TEXT NOT NULL,
-                completed BOOLEAN NOT NULL
-            )",
-            [],
-        ).unwrap();
-        conn
+    fn setup_test_repo() -> SqliteRepository {
+        let mut conn = Connection::open(":memory:").unwrap();
+        migrations::run_migrations(&mut conn).unwrap();
+        SqliteRepository::new(conn)
     }
 
     #[test]
     fn test_add_task() -> Result<()> {
-        let conn = setup_test_db();
-        add_task(&conn, "Test task".to_string())?;
-        let mut stmt = conn.prepare("SELECT description, completed FROM tasks WHERE id = 1")?;
-        let task = stmt.query_row([], |row| {
-            Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
-        })?;
-        assert_eq!(task, ("Test task".to_string(), false));
+        let repo = setup_test_repo();
+        add_task(&repo, "Test task".to_string(), None, None, None, OutputFormat::Text)?;
+        let task = repo.get_task(1)?;
+        assert_eq!(task.description, "Test task");
+        assert!(task.finished_at.is_none());
         Ok(())
     }
 
     #[test]
     fn test_complete_task() -> Result<()> {
-        let conn = setup_test_db();
-        add_task(&conn, "Test task".to_string())?;
-        complete_task(&conn, 1)?;
-        let completed: bool = conn.query_row(
-            "SELECT completed FROM tasks WHERE id = 1",
-            [],
-            |row| row.get(0),
-        )?;
-        assert!(completed);
+        let repo = setup_test_repo();
+        add_task(&repo, "Test task".to_string(), None, None, None, OutputFormat::Text)?;
+        complete_task(&repo, None, Some(1), OutputFormat::Text)?;
+        let task = repo.get_task(1)?;
+        assert!(task.finished_at.is_some());
         Ok(())
     }
 
     #[test]
     fn test_delete_task() -> Result<()> {
-        let conn = setup_test_db();
-        add_task(&conn, "Test task".to_string())?;
-        delete_task(&conn, 1)?;
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0))?;
-        assert_eq!(count, 0);
+        let repo = setup_test_repo();
+        add_task(&repo, "Test task".to_string(), None, None, None, OutputFormat::Text)?;
+        delete_task(&repo, None, Some(1), OutputFormat::Text)?;
+        assert!(matches!(repo.get_task(1), Err(error::Error::NotFound)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_complete_by_display_index() -> Result<()> {
+        let repo = setup_test_repo();
+        add_task(&repo, "First".to_string(), None, None, None, OutputFormat::Text)?;
+        add_task(&repo, "Second".to_string(), None, None, None, OutputFormat::Text)?;
+        // Display index 2 happens to equal the db id here; resolve_target is
+        // what's actually under test, not the coincidence.
+        complete_task(&repo, Some(2), None, OutputFormat::Text)?;
+        let task = repo.get_task(2)?;
+        assert!(task.finished_at.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_complete_by_display_index_disambiguates_from_id() -> Result<()> {
+        let repo = setup_test_repo();
+        add_task(&repo, "First".to_string(), None, None, None, OutputFormat::Text)?;
+        add_task(&repo, "Second".to_string(), None, None, None, OutputFormat::Text)?;
+        add_task(&repo, "Third".to_string(), None, None, None, OutputFormat::Text)?;
+        // Completing db id 1 re-indexes the remaining active tasks, so
+        // display index 2 now points at db id 3, not db id 2.
+        complete_task(&repo, None, Some(1), OutputFormat::Text)?;
+        let id = resolve_target(&repo, Some(2), None)?;
+        assert_eq!(id, 3);
+        let id = resolve_target(&repo, None, Some(2))?;
+        assert_eq!(id, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_complete_missing_display_index_reports_not_found() -> Result<()> {
+        let repo = setup_test_repo();
+        // No tasks exist, so display index 1 can't resolve to anything. This
+        // must report through print_mutation_result (and so return Ok, not
+        // propagate a raw error) the same way an unmatched --id does.
+        complete_task(&repo, Some(1), None, OutputFormat::Text)?;
+        complete_task(&repo, Some(1), None, OutputFormat::Json)?;
         Ok(())
     }
 
     #[test]
     fn test_list_tasks_empty() -> Result<()> {
-        let conn = setup_test_db();
-        // Redirect stdout to capture output
-        // let output = std::io::sink();
-        let _ = list_tasks(&conn)?;
-        // Since no tasks, no assertion on output; just ensure it runs
+        let repo = setup_test_repo();
+        list_tasks(&repo, OutputFormat::Text)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_tasks_json_format() -> Result<()> {
+        let repo = setup_test_repo();
+        add_task(&repo, "Test task".to_string(), None, None, None, OutputFormat::Text)?;
+        list_tasks(&repo, OutputFormat::Json)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_due_marks_success() -> Result<()> {
+        let repo = setup_test_repo();
+        let due = OffsetDateTime::now_utc() - time::Duration::seconds(1);
+        let id = repo.schedule_task(
+            NewTask {
+                description: "Say hi".to_string(),
+                project: None,
+                link: None,
+                dir_path: None,
+            },
+            "true".to_string(),
+            due,
+            1,
+        )?;
+        run_due(&repo)?;
+        let task = repo.get_task(id)?;
+        assert!(task.finished_at.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_due_marks_failed_after_max_attempts() -> Result<()> {
+        let repo = setup_test_repo();
+        let due = OffsetDateTime::now_utc() - time::Duration::seconds(1);
+        let id = repo.schedule_task(
+            NewTask {
+                description: "Say hi".to_string(),
+                project: None,
+                link: None,
+                dir_path: None,
+            },
+            "false".to_string(),
+            due,
+            1,
+        )?;
+        run_due(&repo)?;
+        let task = repo.get_task(id)?;
+        assert!(task.failed_at.is_some());
+        assert_eq!(task.attempts, 1);
         Ok(())
     }
 }
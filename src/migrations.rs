@@ -0,0 +1,180 @@
+use rusqlite::{params, Connection, Result, Transaction};
+
+/// Ordered schema migrations, applied sequentially starting from whatever
+/// version is stored in `_tasker_info`. Each entry is a single SQL batch;
+/// append new entries here rather than editing earlier ones, so the history
+/// stays replayable against existing `tasks.db` files.
+const MIGRATIONS: &[&str] = &[
+    // v1: original flat checklist schema.
+    "CREATE TABLE IF NOT EXISTS tasks (
+        id INTEGER PRIMARY KEY,
+        description TEXT NOT NULL,
+        completed BOOLEAN NOT NULL
+    )",
+    // v2: project/link/dir metadata plus created_at/finished_at timestamps.
+    "ALTER TABLE tasks ADD COLUMN project TEXT;
+     ALTER TABLE tasks ADD COLUMN link TEXT;
+     ALTER TABLE tasks ADD COLUMN dir_path TEXT;
+     ALTER TABLE tasks ADD COLUMN created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP;
+     ALTER TABLE tasks ADD COLUMN finished_at DATETIME;",
+    // v3: stable display indices for not-yet-completed tasks.
+    "CREATE VIEW active_tasks AS
+     SELECT *, row_number() OVER (ORDER BY created_at) AS idx
+     FROM tasks
+     WHERE finished_at IS NULL;",
+    // v4: deferred/scheduled tasks with retry tracking.
+    "ALTER TABLE tasks ADD COLUMN scheduled_at DATETIME;
+     ALTER TABLE tasks ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE tasks ADD COLUMN max_attempts INTEGER NOT NULL DEFAULT 1;
+     ALTER TABLE tasks ADD COLUMN failed_at DATETIME;",
+    // v5: drop the `completed` flag now that `finished_at IS NOT NULL` is the
+    // single source of truth for completion.
+    "ALTER TABLE tasks DROP COLUMN completed;",
+    // v6: explicit shell command for scheduled tasks, distinct from the
+    // free-text `description` every other code path treats as a display label.
+    "ALTER TABLE tasks ADD COLUMN command TEXT;",
+];
+
+fn table_exists(tx: &Transaction, name: &str) -> Result<bool> {
+    let count: i64 = tx.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![name],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn view_exists(tx: &Transaction, name: &str) -> Result<bool> {
+    let count: i64 = tx.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'view' AND name = ?1",
+        params![name],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn tasks_has_column(tx: &Transaction, column: &str) -> Result<bool> {
+    let mut stmt = tx.prepare("PRAGMA table_info(tasks)")?;
+    let has_column = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+    Ok(has_column)
+}
+
+/// Infers how far a `tasks.db` has already progressed through [`MIGRATIONS`]
+/// by inspecting its actual schema, for databases that predate `_tasker_info`
+/// (including ones created by ad hoc `CREATE TABLE`/`ALTER TABLE` statements
+/// from before this migration runner existed) rather than assuming version 0.
+fn detect_existing_version(tx: &Transaction) -> Result<i64> {
+    if !table_exists(tx, "tasks")? {
+        return Ok(0);
+    }
+    let mut version = 1;
+    if tasks_has_column(tx, "project")? {
+        version = 2;
+    }
+    if view_exists(tx, "active_tasks")? {
+        version = 3;
+    }
+    if tasks_has_column(tx, "scheduled_at")? {
+        version = 4;
+    }
+    if !tasks_has_column(tx, "completed")? {
+        version = 5;
+    }
+    if tasks_has_column(tx, "command")? {
+        version = 6;
+    }
+    Ok(version)
+}
+
+/// Applies any migrations newer than the version stored in `_tasker_info`,
+/// creating that table on first run. Runs inside a single transaction so a
+/// partially-applied migration can never be observed.
+pub fn run_migrations(conn: &mut Connection) -> Result<()> {
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS _tasker_info (version INTEGER NOT NULL)",
+        [],
+    )?;
+    let stored_version: Option<i64> = tx
+        .query_row("SELECT version FROM _tasker_info", [], |row| row.get(0))
+        .ok();
+    let version = match stored_version {
+        Some(version) => version,
+        None => {
+            let detected = detect_existing_version(&tx)?;
+            tx.execute(
+                "INSERT INTO _tasker_info (version) VALUES (?1)",
+                params![detected],
+            )?;
+            detected
+        }
+    };
+
+    let mut version = version as usize;
+    for migration in MIGRATIONS.iter().skip(version) {
+        tx.execute_batch(migration)?;
+        version += 1;
+        tx.execute(
+            "UPDATE _tasker_info SET version = ?1",
+            params![version as i64],
+        )?;
+    }
+
+    tx.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_migrations_is_idempotent() {
+        let mut conn = Connection::open(":memory:").unwrap();
+        run_migrations(&mut conn).unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        let version: i64 = conn
+            .query_row("SELECT version FROM _tasker_info", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+    }
+
+    /// Reproduces a `tasks.db` produced by the chunk0-1..chunk0-3 commits:
+    /// the v2/v3 schema (metadata columns + `active_tasks` view) applied
+    /// directly, with no `_tasker_info` table ever created.
+    #[test]
+    fn test_run_migrations_handles_pre_tasker_info_schema() {
+        let mut conn = Connection::open(":memory:").unwrap();
+        conn.execute_batch(
+            "CREATE TABLE tasks (
+                id INTEGER PRIMARY KEY,
+                description TEXT NOT NULL,
+                completed BOOLEAN NOT NULL,
+                project TEXT,
+                link TEXT,
+                dir_path TEXT,
+                created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                finished_at DATETIME
+             );
+             CREATE VIEW active_tasks AS
+                SELECT *, row_number() OVER (ORDER BY created_at) AS idx
+                FROM tasks
+                WHERE finished_at IS NULL;
+             INSERT INTO tasks (description, completed) VALUES ('legacy task', 0);",
+        )
+        .unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        let version: i64 = conn
+            .query_row("SELECT version FROM _tasker_info", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+        assert!(tasks_has_column(&conn.unchecked_transaction().unwrap(), "scheduled_at").unwrap());
+        assert!(!tasks_has_column(&conn.unchecked_transaction().unwrap(), "completed").unwrap());
+    }
+}
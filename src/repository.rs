@@ -0,0 +1,244 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::error::Error;
+use crate::from_row::row_extract;
+
+#[derive(Serialize, Deserialize)]
+pub struct Task {
+    pub id: u32,
+    pub description: String,
+    pub project: Option<String>,
+    pub link: Option<String>,
+    pub dir_path: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub finished_at: Option<OffsetDateTime>,
+    /// When set, this task is deferred work not due until this time (see `run-due`).
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub scheduled_at: Option<OffsetDateTime>,
+    /// Shell command `run-due` executes for a scheduled task; `None` for plain checklist tasks.
+    pub command: Option<String>,
+    pub attempts: u32,
+    pub max_attempts: u32,
+    /// Set once `attempts` has exhausted `max_attempts` without success.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub failed_at: Option<OffsetDateTime>,
+    /// Contiguous 1..N position among not-yet-completed tasks, as surfaced by `list`.
+    /// `None` for already-completed tasks, which are shown by their real id instead.
+    pub display_idx: Option<u32>,
+}
+
+/// Fields needed to create a new task; `id`/timestamps are assigned by the repository.
+pub struct NewTask {
+    pub description: String,
+    pub project: Option<String>,
+    pub link: Option<String>,
+    pub dir_path: Option<String>,
+}
+
+/// Base of the exponential backoff applied to failed scheduled tasks: after its
+/// n-th failed attempt a task is rescheduled `BASE_BACKOFF_SECS * 2^n` seconds
+/// later, so the first retry waits 60 seconds.
+const BASE_BACKOFF_SECS: i64 = 30;
+
+/// Storage-agnostic interface for task persistence.
+///
+/// Command handlers in `main.rs` talk to this trait instead of a concrete
+/// `Connection`, so a different backend (in-memory, JSON file, ...) can be
+/// swapped in without touching command logic or tests.
+pub trait Repository {
+    fn get_tasks(&self) -> Result<Vec<Task>, Error>;
+    fn get_task(&self, id: u32) -> Result<Task, Error>;
+    fn insert_task(&self, task: NewTask) -> Result<u32, Error>;
+    fn update_task(&self, id: u32, finished_at: OffsetDateTime) -> Result<(), Error>;
+    fn remove_task(&self, id: u32) -> Result<(), Error>;
+    /// Resolves a display index (as shown by `get_tasks`) to the real id of
+    /// the active task currently holding that position. Errors with
+    /// [`Error::NotFound`] rather than guessing if no active task has it.
+    fn resolve_display_index(&self, idx: u32) -> Result<u32, Error>;
+    /// Inserts a task that runs `command` in a shell once due at `scheduled_at`,
+    /// failing permanently after `max_attempts`.
+    fn schedule_task(
+        &self,
+        task: NewTask,
+        command: String,
+        scheduled_at: OffsetDateTime,
+        max_attempts: u32,
+    ) -> Result<u32, Error>;
+    /// Scheduled tasks due at or before `now` that haven't finished or failed yet.
+    fn get_due_tasks(&self, now: OffsetDateTime) -> Result<Vec<Task>, Error>;
+    /// Records a failed attempt, rescheduling with exponential backoff or marking
+    /// the task failed once `max_attempts` is exhausted.
+    fn record_failure(&self, id: u32, now: OffsetDateTime) -> Result<(), Error>;
+}
+
+pub struct SqliteRepository {
+    conn: Connection,
+}
+
+impl SqliteRepository {
+    pub fn new(conn: Connection) -> Self {
+        Self { conn }
+    }
+
+    const SELECT_COLUMNS: &'static str = "t.id, t.description, t.project, t.link,
+         t.dir_path, t.created_at, t.finished_at, t.scheduled_at, t.command, t.attempts,
+         t.max_attempts, t.failed_at, a.idx";
+}
+
+impl Repository for SqliteRepository {
+    fn get_tasks(&self) -> Result<Vec<Task>, Error> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tasks t
+             LEFT JOIN active_tasks a ON a.id = t.id
+             ORDER BY (t.finished_at IS NOT NULL), a.idx, t.finished_at",
+            Self::SELECT_COLUMNS
+        ))?;
+        let tasks = stmt
+            .query_map([], row_extract::<Task>)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(tasks)
+    }
+
+    fn get_task(&self, id: u32) -> Result<Task, Error> {
+        let task = self.conn.query_row(
+            &format!(
+                "SELECT {} FROM tasks t
+                 LEFT JOIN active_tasks a ON a.id = t.id
+                 WHERE t.id = ?1",
+                Self::SELECT_COLUMNS
+            ),
+            params![id],
+            row_extract::<Task>,
+        )?;
+        Ok(task)
+    }
+
+    fn insert_task(&self, task: NewTask) -> Result<u32, Error> {
+        let now = OffsetDateTime::now_utc();
+        self.conn
+            .execute(
+                "INSERT INTO tasks (description, project, link, dir_path, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![task.description, task.project, task.link, task.dir_path, now],
+            )
+            .map_err(|err| Error::InsertData(err.to_string()))?;
+        Ok(self.conn.last_insert_rowid() as u32)
+    }
+
+    fn update_task(&self, id: u32, finished_at: OffsetDateTime) -> Result<(), Error> {
+        let rows_affected = self
+            .conn
+            .execute(
+                "UPDATE tasks SET finished_at = ?1 WHERE id = ?2",
+                params![finished_at, id],
+            )
+            .map_err(|err| Error::UpdateData(err.to_string()))?;
+        if rows_affected == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    fn remove_task(&self, id: u32) -> Result<(), Error> {
+        let rows_affected = self
+            .conn
+            .execute("DELETE FROM tasks WHERE id = ?1", params![id])
+            .map_err(|err| Error::RemoveData(err.to_string()))?;
+        if rows_affected == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    fn resolve_display_index(&self, idx: u32) -> Result<u32, Error> {
+        self.conn
+            .query_row(
+                "SELECT id FROM active_tasks WHERE idx = ?1",
+                params![idx],
+                row_extract::<u32>,
+            )
+            .map_err(|_| Error::NotFound)
+    }
+
+    fn schedule_task(
+        &self,
+        task: NewTask,
+        command: String,
+        scheduled_at: OffsetDateTime,
+        max_attempts: u32,
+    ) -> Result<u32, Error> {
+        let now = OffsetDateTime::now_utc();
+        self.conn
+            .execute(
+                "INSERT INTO tasks
+                    (description, project, link, dir_path, created_at,
+                     scheduled_at, command, max_attempts)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    task.description,
+                    task.project,
+                    task.link,
+                    task.dir_path,
+                    now,
+                    scheduled_at,
+                    command,
+                    max_attempts,
+                ],
+            )
+            .map_err(|err| Error::InsertData(err.to_string()))?;
+        Ok(self.conn.last_insert_rowid() as u32)
+    }
+
+    fn get_due_tasks(&self, now: OffsetDateTime) -> Result<Vec<Task>, Error> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tasks t
+             LEFT JOIN active_tasks a ON a.id = t.id
+             WHERE t.scheduled_at IS NOT NULL
+               AND t.scheduled_at <= ?1
+               AND t.finished_at IS NULL
+               AND t.failed_at IS NULL
+             ORDER BY t.scheduled_at",
+            Self::SELECT_COLUMNS
+        ))?;
+        let tasks = stmt
+            .query_map(params![now], row_extract::<Task>)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(tasks)
+    }
+
+    fn record_failure(&self, id: u32, now: OffsetDateTime) -> Result<(), Error> {
+        let (attempts, max_attempts): (u32, u32) = self
+            .conn
+            .query_row(
+                "SELECT attempts, max_attempts FROM tasks WHERE id = ?1",
+                params![id],
+                row_extract::<(u32, u32)>,
+            )
+            .map_err(|_| Error::NotFound)?;
+        let attempts = attempts + 1;
+
+        let rows_affected = if attempts >= max_attempts {
+            self.conn.execute(
+                "UPDATE tasks SET attempts = ?1, failed_at = ?2 WHERE id = ?3",
+                params![attempts, now, id],
+            )
+        } else {
+            let backoff_secs = BASE_BACKOFF_SECS * 2i64.pow(attempts);
+            let next_attempt_at = now + time::Duration::seconds(backoff_secs);
+            self.conn.execute(
+                "UPDATE tasks SET attempts = ?1, scheduled_at = ?2 WHERE id = ?3",
+                params![attempts, next_attempt_at, id],
+            )
+        }
+        .map_err(|err| Error::UpdateData(err.to_string()))?;
+
+        if rows_affected == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+}